@@ -1,339 +1,786 @@
-use std::sync::{Arc, Mutex};
-
-use vulkan_engine::{AbstractEngine, GraphicalEngine, LogicalDevice, SVertex};
-use vulkano::{
-    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
-    command_buffer::{
-        AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer,
-        RenderPassBeginInfo, SubpassContents,
-    },
-    memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator},
-    pipeline::{
-        graphics::{
-            input_assembly::InputAssemblyState,
-            vertex_input::Vertex,
-            viewport::{Viewport, ViewportState},
-        },
-        GraphicsPipeline,
-    },
-    render_pass::{Framebuffer, RenderPass, Subpass},
-    shader::ShaderModule,
-    swapchain::{self, AcquireError, SwapchainPresentInfo},
-    sync::{self, FlushError, GpuFuture},
-};
-use vulkano_win::VkSurfaceBuild;
-use winit::{
-    dpi::{PhysicalSize, Pixel},
-    event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
-};
-
-mod shader_vertex {
-    vulkano_shaders::shader! {ty: "vertex", path: "shaders/007_basic_triangle.vert"}
-}
-
-mod shader_fragment {
-    vulkano_shaders::shader! {ty: "fragment", path: "shaders/007_basic_triangle.frag"}
-}
-
-fn create_viewport<T: Pixel>(physical_size: PhysicalSize<T>) -> Viewport {
-    Viewport {
-        origin: [0.0, 0.0],
-        dimensions: physical_size.into(),
-        depth_range: 0.0..1.0,
-    }
-}
-
-fn create_pipeline<T: Pixel>(
-    vertex_shader: Arc<ShaderModule>,
-    fragment_shader: Arc<ShaderModule>,
-    physical_size: PhysicalSize<T>,
-    render_pass: Arc<RenderPass>,
-    logical_device: Arc<LogicalDevice>,
-) -> Arc<GraphicsPipeline> {
-    let viewport = create_viewport(physical_size);
-
-    GraphicsPipeline::start()
-        .vertex_input_state(SVertex::per_vertex())
-        .vertex_shader(vertex_shader.entry_point("main").unwrap(), ())
-        .input_assembly_state(InputAssemblyState::new())
-        .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([viewport]))
-        .fragment_shader(fragment_shader.entry_point("main").unwrap(), ())
-        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
-        .build(logical_device.get_device())
-        .unwrap()
-}
-
-fn create_command_buffers(
-    frame_buffers: Vec<Arc<Framebuffer>>,
-    graphical_engine: Arc<Mutex<GraphicalEngine>>,
-    pipeline: Arc<GraphicsPipeline>,
-    vertex_buffer: Subbuffer<[SVertex]>,
-) -> Vec<Arc<PrimaryAutoCommandBuffer>> {
-    frame_buffers
-        .iter()
-        .map(|framebuffer| {
-            let engine_arc = graphical_engine.lock().unwrap();
-
-            let mut builder = AutoCommandBufferBuilder::primary(
-                &engine_arc.get_command_buffer_allocator(),
-                engine_arc.get_logical_device().get_queue_family_index(),
-                CommandBufferUsage::MultipleSubmit, // don't forget to write the correct buffer usage
-            )
-            .unwrap();
-
-            builder
-                .begin_render_pass(
-                    RenderPassBeginInfo {
-                        clear_values: vec![Some([0.1, 0.1, 0.1, 1.0].into())],
-                        ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
-                    },
-                    SubpassContents::Inline,
-                )
-                .unwrap()
-                .bind_pipeline_graphics(pipeline.clone())
-                .bind_vertex_buffers(0, vertex_buffer.clone())
-                .draw(vertex_buffer.len() as u32, 1, 0, 0)
-                .unwrap()
-                .end_render_pass()
-                .unwrap();
-
-            Arc::new(builder.build().unwrap())
-        })
-        .collect()
-}
-
-pub fn main() {
-    env_logger::init();
-    log::info!(
-        "Logger initialized at max level set to {}",
-        log::max_level()
-    );
-    log::info!("007 - Basic Triangle");
-
-    // Vulkan instance
-    let instance = GraphicalEngine::make_instance();
-
-    // Window
-    let event_loop = EventLoop::new();
-    let surface = WindowBuilder::new()
-        .build_vk_surface(&event_loop, instance.clone()) // Not all Winit versions are compatible with vulkano-win apparently. Make sure they work together or imports won't work!
-        .expect("failed to create window surface");
-
-    // Engine
-    let graphical_engine = Arc::new(Mutex::new(GraphicalEngine::new(instance, surface.clone())));
-
-    // Memory Allocator
-    let memory_allocator = StandardMemoryAllocator::new_default(
-        graphical_engine
-            .lock()
-            .unwrap()
-            .get_logical_device()
-            .get_device(),
-    );
-
-    // Set vertices for triangle
-    let vertex1 = SVertex {
-        position: [-0.5, -0.5],
-    };
-    let vertex2 = SVertex {
-        position: [0.0, 0.5],
-    };
-    let vertex3 = SVertex {
-        position: [0.5, -0.25],
-    };
-
-    // Create vertex buffer
-    let vertex_buffer = Buffer::from_iter(
-        &memory_allocator,
-        BufferCreateInfo {
-            usage: BufferUsage::VERTEX_BUFFER,
-            ..Default::default()
-        },
-        AllocationCreateInfo {
-            usage: MemoryUsage::Upload,
-            ..Default::default()
-        },
-        vec![vertex1, vertex2, vertex3].into_iter(),
-    )
-    .unwrap();
-
-    // RenderPass
-    let render_pass = graphical_engine.lock().unwrap().create_render_pass();
-
-    // Shaders
-    let vertex_shader = shader_vertex::load(
-        graphical_engine
-            .lock()
-            .unwrap()
-            .get_logical_device()
-            .get_device(),
-    )
-    .expect("failed to create vertex shader module");
-    let fragment_shader = shader_fragment::load(
-        graphical_engine
-            .lock()
-            .unwrap()
-            .get_logical_device()
-            .get_device(),
-    )
-    .expect("failed to create fragment shader module");
-
-    // Pipeline
-    let pipeline = Mutex::new(create_pipeline(
-        vertex_shader.clone(),
-        fragment_shader.clone(),
-        PhysicalSize {
-            width: 1024.0,
-            height: 1024.0,
-        },
-        render_pass.clone(),
-        graphical_engine.lock().unwrap().get_logical_device(),
-    ));
-
-    // Framebuffer
-    let frame_buffers = Mutex::new(
-        graphical_engine
-            .lock()
-            .unwrap()
-            .create_frame_buffers(render_pass.clone()),
-    );
-
-    // Command Buffers
-    let mut command_buffers: Vec<Arc<PrimaryAutoCommandBuffer>> = create_command_buffers(
-        frame_buffers.lock().unwrap().clone(),
-        graphical_engine.clone(),
-        pipeline.lock().unwrap().clone(),
-        vertex_buffer.clone(),
-    );
-
-    // Window variables
-    let mut window_resize_request: Option<PhysicalSize<u32>> = None;
-    let mut recreate_swapchain = false;
-
-    // Hijack thread and open window
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = winit::event_loop::ControlFlow::Wait;
-
-        match event {
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
-                ..
-            } => {
-                *control_flow = ControlFlow::Exit;
-
-                // Kills the engine (and this main thread) and frees resources.
-                // Otherwise, SEGFAULT's will occur on exit.
-                graphical_engine.lock().unwrap().kill();
-            }
-            Event::WindowEvent {
-                event: WindowEvent::Resized(new_size),
-                ..
-            } => {
-                window_resize_request = Some(new_size);
-            }
-            Event::RedrawEventsCleared => {
-                log::debug!("RedrawEventsCleared");
-                log::debug!("Resized: {:?}", window_resize_request);
-                log::debug!("Recreate: {}", recreate_swapchain);
-
-                if window_resize_request.is_some() || recreate_swapchain {
-                    match graphical_engine
-                        .lock()
-                        .unwrap()
-                        .recreate_swap_chain_and_images(render_pass.clone())
-                    {
-                        Some(new_frame_buffers) => {
-                            let mut frame_buffers_lock = frame_buffers.lock().unwrap();
-                            *frame_buffers_lock = new_frame_buffers;
-
-                            recreate_swapchain = false;
-                        }
-                        None => {
-                            // Something did go wrong while recreating the swapchain.
-                            // There is no ideal way of handling this, our best bet is that this is a single occurrence.
-                            // If it is, we just need to recreate the swapchain again and run the 'resize window' code again.
-                            // If not, this error will probably repeat forever and crash the program eventually.
-
-                            log::error!("Failed recreating SwapChain! Retrying ...");
-                            return;
-                        }
-                    };
-
-                    if window_resize_request.is_some() {
-                        let new_pipeline = create_pipeline(
-                            vertex_shader.clone(),
-                            fragment_shader.clone(),
-                            window_resize_request.unwrap(),
-                            render_pass.clone(),
-                            graphical_engine.lock().unwrap().get_logical_device(),
-                        );
-                        let mut pipeline_lock = pipeline.lock().unwrap();
-                        *pipeline_lock = new_pipeline;
-
-                        command_buffers = create_command_buffers(
-                            frame_buffers.lock().unwrap().clone(),
-                            graphical_engine.clone(),
-                            pipeline_lock.clone(),
-                            vertex_buffer.clone(),
-                        );
-
-                        window_resize_request = None;
-                    }
-                }
-
-                let (image_i, suboptimal, acquire_future) = match swapchain::acquire_next_image(
-                    graphical_engine.lock().unwrap().get_swap_chain().clone(),
-                    None,
-                ) {
-                    Ok(r) => r,
-                    Err(AcquireError::OutOfDate) => {
-                        recreate_swapchain = true;
-                        return;
-                    }
-                    Err(e) => panic!("Failed to acquire next image: {:?}", e),
-                };
-
-                if suboptimal {
-                    recreate_swapchain = true;
-                }
-
-                let engine_arc = graphical_engine.lock().unwrap();
-                let execution = sync::now(engine_arc.get_logical_device().get_device())
-                    // Wait for the image to actually become available
-                    .join(acquire_future)
-                    // Run `CommandBuffer` for that image
-                    .then_execute(
-                        engine_arc.get_logical_device().get_first_queue(),
-                        command_buffers[image_i as usize].clone(),
-                    )
-                    .unwrap()
-                    // Finish drawing and present the image on the swapchain
-                    .then_swapchain_present(
-                        engine_arc.get_logical_device().get_first_queue(),
-                        SwapchainPresentInfo::swapchain_image_index(
-                            engine_arc.get_swap_chain(),
-                            image_i,
-                        ),
-                    )
-                    .then_signal_fence_and_flush();
-
-                match execution {
-                    Ok(future) => future.wait(None).unwrap(), // Wait for the GPU to finish
-                    Err(FlushError::OutOfDate) => {
-                        // Something did go wrong, recreate swapchain
-                        recreate_swapchain = true;
-                    }
-                    Err(e) => {
-                        // Unknown error
-                        log::error!("Failed to flush future: {:?}", e);
-                    }
-                }
-            }
-            Event::RedrawRequested(_) => {}
-            Event::MainEventsCleared => {}
-            _ => (),
-        }
-    });
-}
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use cgmath::{perspective, Deg, Matrix4, Point3, Vector3};
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebouncedEvent};
+use vulkan_engine::{AbstractEngine, GraphicalEngine, LogicalDevice, RenderOutcome};
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer,
+        RenderPassBeginInfo, SubpassContents,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator},
+    pipeline::{
+        graphics::{
+            input_assembly::InputAssemblyState,
+            vertex_input::Vertex,
+            viewport::{Viewport, ViewportState},
+        },
+        GraphicsPipeline,
+    },
+    render_pass::{Framebuffer, RenderPass, Subpass},
+    shader::ShaderModule,
+    swapchain::PresentMode,
+};
+use vulkano_win::VkSurfaceBuild;
+use winit::{
+    dpi::{PhysicalSize, Pixel},
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::WindowBuilder,
+};
+
+mod shader_vertex_instanced {
+    vulkano_shaders::shader! {ty: "vertex", path: "shaders/007_basic_triangle_instanced.vert"}
+}
+
+mod shader_fragment_instanced {
+    vulkano_shaders::shader! {ty: "fragment", path: "shaders/007_basic_triangle_instanced.frag"}
+}
+
+// Bound to vertex slot 0: an object-space position plus a per-vertex color,
+// Gouraud-shaded across the quad by the fragment shader.
+#[derive(BufferContents, Vertex, Clone, Copy)]
+#[repr(C)]
+struct SVertexColor {
+    #[format(R32G32_SFLOAT)]
+    position: [f32; 2],
+    #[format(R32G32B32_SFLOAT)]
+    color: [f32; 3],
+}
+
+// Bound to vertex slot 1, one entry per draw instance. The model matrix is
+// decomposed into four vec4 columns since GLSL has no per-vertex mat4 input.
+#[derive(BufferContents, Vertex, Clone, Copy)]
+#[repr(C)]
+struct SInstanceData {
+    #[format(R32G32B32A32_SFLOAT)]
+    model_col0: [f32; 4],
+    #[format(R32G32B32A32_SFLOAT)]
+    model_col1: [f32; 4],
+    #[format(R32G32B32A32_SFLOAT)]
+    model_col2: [f32; 4],
+    #[format(R32G32B32A32_SFLOAT)]
+    model_col3: [f32; 4],
+}
+
+impl SInstanceData {
+    // Builds the per-instance data for a simple 2D translation; the
+    // model matrix itself is still just a translation, the camera/projection
+    // pair below is what turns it into clip space.
+    fn translation(x: f32, y: f32) -> Self {
+        Self {
+            model_col0: [1.0, 0.0, 0.0, 0.0],
+            model_col1: [0.0, 1.0, 0.0, 0.0],
+            model_col2: [0.0, 0.0, 1.0, 0.0],
+            model_col3: [x, y, 0.0, 1.0],
+        }
+    }
+}
+
+// Minimal perspective camera producing the view/projection matrices handed
+// to the vertex shader as push constants.
+struct Camera {
+    eye: Point3<f32>,
+    target: Point3<f32>,
+    up: Vector3<f32>,
+    fovy: Deg<f32>,
+    near: f32,
+    far: f32,
+}
+
+impl Camera {
+    fn view_projection(&self, aspect: f32) -> (Matrix4<f32>, Matrix4<f32>) {
+        let view = Matrix4::look_at_rh(self.eye, self.target, self.up);
+        let mut projection = perspective(self.fovy, aspect, self.near, self.far);
+
+        // cgmath::perspective assumes OpenGL's Y-up clip space; Vulkan's is
+        // Y-down, so without this the whole scene renders vertically flipped.
+        projection[1][1] *= -1.0;
+
+        (view, projection)
+    }
+}
+
+fn create_viewport<T: Pixel>(physical_size: PhysicalSize<T>) -> Viewport {
+    Viewport {
+        origin: [0.0, 0.0],
+        dimensions: physical_size.into(),
+        depth_range: 0.0..1.0,
+    }
+}
+
+fn create_pipeline<T: Pixel>(
+    vertex_shader: Arc<ShaderModule>,
+    fragment_shader: Arc<ShaderModule>,
+    physical_size: PhysicalSize<T>,
+    render_pass: Arc<RenderPass>,
+    logical_device: Arc<LogicalDevice>,
+) -> Arc<GraphicsPipeline> {
+    let viewport = create_viewport(physical_size);
+
+    GraphicsPipeline::start()
+        .vertex_input_state([SVertexColor::per_vertex(), SInstanceData::per_instance()])
+        .vertex_shader(vertex_shader.entry_point("main").unwrap(), ())
+        .input_assembly_state(InputAssemblyState::new())
+        .viewport_state(ViewportState::viewport_fixed_scissor_irrelevant([viewport]))
+        .fragment_shader(fragment_shader.entry_point("main").unwrap(), ())
+        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+        .build(logical_device.get_device())
+        .unwrap()
+}
+
+fn create_instanced_command_buffers(
+    frame_buffers: Vec<Arc<Framebuffer>>,
+    graphical_engine: Arc<Mutex<GraphicalEngine>>,
+    pipeline: Arc<GraphicsPipeline>,
+    vertex_buffer: Subbuffer<[SVertexColor]>,
+    index_buffer: Subbuffer<[u32]>,
+    instance_buffer: Subbuffer<[SInstanceData]>,
+    camera: &Camera,
+    aspect: f32,
+    clear_color: [f32; 4],
+) -> Vec<Arc<PrimaryAutoCommandBuffer>> {
+    let (view, projection) = camera.view_projection(aspect);
+    let push_constants = shader_vertex_instanced::PushConstants {
+        view: view.into(),
+        projection: projection.into(),
+    };
+
+    frame_buffers
+        .iter()
+        .map(|framebuffer| {
+            let engine_arc = graphical_engine.lock().unwrap();
+
+            let mut builder = AutoCommandBufferBuilder::primary(
+                &engine_arc.get_command_buffer_allocator(),
+                engine_arc.get_logical_device().get_queue_family_index(),
+                CommandBufferUsage::MultipleSubmit,
+            )
+            .unwrap();
+
+            builder
+                .begin_render_pass(
+                    RenderPassBeginInfo {
+                        clear_values: vec![Some(clear_color.into())],
+                        ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+                    },
+                    SubpassContents::Inline,
+                )
+                .unwrap()
+                .bind_pipeline_graphics(pipeline.clone())
+                .push_constants(pipeline.layout().clone(), 0, push_constants)
+                .bind_vertex_buffers(0, (vertex_buffer.clone(), instance_buffer.clone()))
+                .bind_index_buffer(index_buffer.clone())
+                .draw_indexed(
+                    index_buffer.len() as u32,
+                    instance_buffer.len() as u32,
+                    0,
+                    0,
+                    0,
+                )
+                .unwrap()
+                .end_render_pass()
+                .unwrap();
+
+            Arc::new(builder.build().unwrap())
+        })
+        .collect()
+}
+
+// What changed on disk, as surfaced by `HotReload`'s debounced watcher.
+enum ReloadEvent {
+    Shaders,
+    Config,
+}
+
+// Watches the shaders/ directory and the engine config file for writes and
+// forwards a debounced ReloadEvent to the render loop.
+struct HotReload {
+    // Kept alive for as long as `HotReload` is, since dropping it stops the watch.
+    _debouncer: notify_debouncer_mini::Debouncer<notify_debouncer_mini::notify::RecommendedWatcher>,
+    events: Receiver<ReloadEvent>,
+}
+
+impl HotReload {
+    fn new(shaders_dir: &Path, config_path: &Path) -> Self {
+        let (tx, events) = mpsc::channel();
+        let config_path_for_watcher = config_path.to_path_buf();
+
+        let mut debouncer = new_debouncer(
+            Duration::from_millis(250),
+            move |result: Result<Vec<DebouncedEvent>, _>| {
+                let paths = match result {
+                    Ok(events) => events,
+                    Err(e) => {
+                        log::error!("Hot-reload watcher error: {:?}", e);
+                        return;
+                    }
+                };
+
+                for event in paths {
+                    let reload_event = if event.path == config_path_for_watcher {
+                        ReloadEvent::Config
+                    } else {
+                        ReloadEvent::Shaders
+                    };
+
+                    if tx.send(reload_event).is_err() {
+                        return;
+                    }
+                }
+            },
+        )
+        .expect("failed to create filesystem watcher");
+
+        debouncer
+            .watcher()
+            .watch(shaders_dir, RecursiveMode::NonRecursive)
+            .expect("failed to watch shaders directory");
+
+        // The config file is allowed to be absent, the same way
+        // `load_render_settings` falls back gracefully when it can't read it,
+        // so deleting it doesn't crash the running window.
+        if config_path.exists() {
+            if let Err(e) = debouncer
+                .watcher()
+                .watch(config_path, RecursiveMode::NonRecursive)
+            {
+                log::warn!(
+                    "Failed to watch engine config file {:?}: {:?}",
+                    config_path,
+                    e
+                );
+            }
+        } else {
+            log::warn!(
+                "Engine config file {:?} does not exist yet, skipping watch for it",
+                config_path
+            );
+        }
+
+        Self {
+            _debouncer: debouncer,
+            events,
+        }
+    }
+
+    fn poll(&self) -> Option<ReloadEvent> {
+        self.events.try_recv().ok()
+    }
+}
+
+// Compiles a GLSL source file to SPIR-V with shaderc and builds a fresh
+// ShaderModule from it.
+fn recompile_shader(
+    path: &Path,
+    kind: shaderc::ShaderKind,
+    logical_device: Arc<LogicalDevice>,
+) -> Option<Arc<ShaderModule>> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| log::error!("Failed to read shader {:?}: {:?}", path, e))
+        .ok()?;
+
+    let compiler = shaderc::Compiler::new()?;
+    let binary = compiler
+        .compile_into_spirv(
+            &source,
+            kind,
+            path.to_str().unwrap_or("<shader>"),
+            "main",
+            None,
+        )
+        .map_err(|e| log::error!("Failed to compile shader {:?}: {:?}", path, e))
+        .ok()?;
+
+    unsafe { ShaderModule::from_words(logical_device.get_device(), binary.as_binary()) }
+        .map_err(|e| log::error!("Failed to create shader module for {:?}: {:?}", path, e))
+        .ok()
+}
+
+// User-facing render configuration, reloaded from the engine config file
+// whenever it changes on disk.
+#[derive(Clone, Copy)]
+struct RenderSettings {
+    clear_color: [f32; 4],
+    present_mode: PresentMode,
+}
+
+// Reads the `clear_color = [r, g, b, a]` and `present_mode = "..."` lines out
+// of the engine config file. Falls back to the previous value line-by-line if
+// the file is missing or a line is malformed, so a bad edit doesn't crash the
+// running window.
+fn load_render_settings(config_path: &Path, fallback: RenderSettings) -> RenderSettings {
+    let Ok(contents) = std::fs::read_to_string(config_path) else {
+        return fallback;
+    };
+
+    let clear_color = contents
+        .lines()
+        .find(|line| line.trim_start().starts_with("clear_color"))
+        .and_then(|line| line.split('[').nth(1))
+        .and_then(|s| s.split(']').next())
+        .map(|values| {
+            values
+                .split(',')
+                .map(|v| v.trim().parse().ok())
+                .collect::<Option<Vec<f32>>>()
+        })
+        .and_then(|parsed| match parsed?.as_slice() {
+            [r, g, b, a] => Some([*r, *g, *b, *a]),
+            _ => None,
+        })
+        .unwrap_or(fallback.clear_color);
+
+    let present_mode = contents
+        .lines()
+        .find(|line| line.trim_start().starts_with("present_mode"))
+        .and_then(|line| line.split('"').nth(1))
+        .and_then(|value| match value {
+            "fifo" => Some(PresentMode::Fifo),
+            "immediate" => Some(PresentMode::Immediate),
+            "mailbox" => Some(PresentMode::Mailbox),
+            _ => None,
+        })
+        .unwrap_or(fallback.present_mode);
+
+    RenderSettings {
+        clear_color,
+        present_mode,
+    }
+}
+
+pub fn main() {
+    env_logger::init();
+    log::info!(
+        "Logger initialized at max level set to {}",
+        log::max_level()
+    );
+    log::info!("007 - Basic Triangle");
+
+    // Vulkan instance
+    let instance = GraphicalEngine::make_instance();
+
+    // Window
+    let event_loop = EventLoop::new();
+    let surface = WindowBuilder::new()
+        .build_vk_surface(&event_loop, instance.clone()) // Not all Winit versions are compatible with vulkano-win apparently. Make sure they work together or imports won't work!
+        .expect("failed to create window surface");
+
+    // Engine
+    let graphical_engine = Arc::new(Mutex::new(GraphicalEngine::new(instance, surface.clone())));
+
+    // Memory Allocator
+    let memory_allocator = StandardMemoryAllocator::new_default(
+        graphical_engine
+            .lock()
+            .unwrap()
+            .get_logical_device()
+            .get_device(),
+    );
+
+    // Set vertices for a quad, 4 corners shared across its 2 triangles
+    // instead of 6 duplicated vertices.
+    let vertex_top_left = SVertexColor {
+        position: [-0.5, 0.5],
+        color: [1.0, 0.0, 0.0],
+    };
+    let vertex_top_right = SVertexColor {
+        position: [0.5, 0.5],
+        color: [0.0, 1.0, 0.0],
+    };
+    let vertex_bottom_right = SVertexColor {
+        position: [0.5, -0.5],
+        color: [0.0, 0.0, 1.0],
+    };
+    let vertex_bottom_left = SVertexColor {
+        position: [-0.5, -0.5],
+        color: [1.0, 1.0, 0.0],
+    };
+
+    // Create vertex buffer
+    let vertex_buffer = Buffer::from_iter(
+        &memory_allocator,
+        BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            usage: MemoryUsage::Upload,
+            ..Default::default()
+        },
+        vec![
+            vertex_top_left,
+            vertex_top_right,
+            vertex_bottom_right,
+            vertex_bottom_left,
+        ]
+        .into_iter(),
+    )
+    .unwrap();
+
+    // Index buffer describing the quad as two triangles over the 4 shared vertices
+    let index_buffer = graphical_engine
+        .lock()
+        .unwrap()
+        .create_index_buffer(&memory_allocator, vec![0, 1, 2, 2, 3, 0]);
+
+    // Per-instance transforms: a small grid of copies of the same quad,
+    // drawn in a single instanced draw call instead of one command buffer
+    // (and submission) per object.
+    let instance_buffer = Buffer::from_iter(
+        &memory_allocator,
+        BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            usage: MemoryUsage::Upload,
+            ..Default::default()
+        },
+        (-2..=2)
+            .flat_map(|x| (-2..=2).map(move |y| (x, y)))
+            .map(|(x, y)| SInstanceData::translation(x as f32 * 0.3, y as f32 * 0.3)),
+    )
+    .unwrap();
+
+    // RenderPass
+    let render_pass = graphical_engine.lock().unwrap().create_render_pass();
+
+    // Engine config, live-reloaded alongside the shaders
+    let config_path = PathBuf::from("engine_config.toml");
+    let mut render_settings = load_render_settings(
+        &config_path,
+        RenderSettings {
+            clear_color: [0.1, 0.1, 0.1, 1.0],
+            present_mode: PresentMode::Fifo,
+        },
+    );
+
+    // Shaders
+    let mut vertex_shader = shader_vertex_instanced::load(
+        graphical_engine
+            .lock()
+            .unwrap()
+            .get_logical_device()
+            .get_device(),
+    )
+    .expect("failed to create vertex shader module");
+    let mut fragment_shader = shader_fragment_instanced::load(
+        graphical_engine
+            .lock()
+            .unwrap()
+            .get_logical_device()
+            .get_device(),
+    )
+    .expect("failed to create fragment shader module");
+
+    // Pipeline
+    let pipeline = Mutex::new(create_pipeline(
+        vertex_shader.clone(),
+        fragment_shader.clone(),
+        PhysicalSize {
+            width: 1024.0,
+            height: 1024.0,
+        },
+        render_pass.clone(),
+        graphical_engine.lock().unwrap().get_logical_device(),
+    ));
+
+    // Camera used to build the MVP matrices pushed to the vertex shader
+    let camera = Camera {
+        eye: Point3::new(0.0, 0.0, 3.0),
+        target: Point3::new(0.0, 0.0, 0.0),
+        up: Vector3::new(0.0, 1.0, 0.0),
+        fovy: Deg(60.0),
+        near: 0.1,
+        far: 100.0,
+    };
+    let mut aspect = 1024.0 / 1024.0;
+    let mut current_size = PhysicalSize::new(1024u32, 1024u32);
+
+    // Framebuffer
+    let frame_buffers = Mutex::new(
+        graphical_engine
+            .lock()
+            .unwrap()
+            .create_frame_buffers(render_pass.clone()),
+    );
+
+    // Command Buffers
+    let mut command_buffers: Vec<Arc<PrimaryAutoCommandBuffer>> = create_instanced_command_buffers(
+        frame_buffers.lock().unwrap().clone(),
+        graphical_engine.clone(),
+        pipeline.lock().unwrap().clone(),
+        vertex_buffer.clone(),
+        index_buffer.clone(),
+        instance_buffer.clone(),
+        &camera,
+        aspect,
+        render_settings.clear_color,
+    );
+
+    // Window variables
+    let mut window_resize_request: Option<PhysicalSize<u32>> = None;
+    let mut recreate_swapchain = false;
+
+    // Watches `shaders/` and the engine config file and reports debounced
+    // changes so they can be picked up without restarting the window.
+    let hot_reload = HotReload::new(Path::new("shaders"), &config_path);
+
+    // Hijack thread and open window
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = winit::event_loop::ControlFlow::Wait;
+
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                *control_flow = ControlFlow::Exit;
+
+                // Cleans up the engine's finished in-flight frame futures
+                // before the window closes.
+                graphical_engine.lock().unwrap().kill();
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Resized(new_size),
+                ..
+            } => {
+                window_resize_request = Some(new_size);
+            }
+            Event::RedrawEventsCleared => {
+                log::debug!("RedrawEventsCleared");
+                log::debug!("Resized: {:?}", window_resize_request);
+                log::debug!("Recreate: {}", recreate_swapchain);
+
+                if window_resize_request.is_some() || recreate_swapchain {
+                    match graphical_engine
+                        .lock()
+                        .unwrap()
+                        .recreate_swap_chain_and_images(render_pass.clone())
+                    {
+                        Some(new_frame_buffers) => {
+                            let mut frame_buffers_lock = frame_buffers.lock().unwrap();
+                            *frame_buffers_lock = new_frame_buffers;
+
+                            recreate_swapchain = false;
+                        }
+                        None => {
+                            // Something did go wrong while recreating the swapchain.
+                            // There is no ideal way of handling this, our best bet is that this is a single occurrence.
+                            // If it is, we just need to recreate the swapchain again and run the 'resize window' code again.
+                            // If not, this error will probably repeat forever and crash the program eventually.
+
+                            log::error!("Failed recreating SwapChain! Retrying ...");
+                            return;
+                        }
+                    };
+
+                    if window_resize_request.is_some() {
+                        let new_size = window_resize_request.unwrap();
+                        current_size = new_size;
+
+                        let new_pipeline = create_pipeline(
+                            vertex_shader.clone(),
+                            fragment_shader.clone(),
+                            new_size,
+                            render_pass.clone(),
+                            graphical_engine.lock().unwrap().get_logical_device(),
+                        );
+                        let mut pipeline_lock = pipeline.lock().unwrap();
+                        *pipeline_lock = new_pipeline;
+
+                        aspect = new_size.width as f32 / new_size.height as f32;
+
+                        command_buffers = create_instanced_command_buffers(
+                            frame_buffers.lock().unwrap().clone(),
+                            graphical_engine.clone(),
+                            pipeline_lock.clone(),
+                            vertex_buffer.clone(),
+                            index_buffer.clone(),
+                            instance_buffer.clone(),
+                            &camera,
+                            aspect,
+                            render_settings.clear_color,
+                        );
+
+                        window_resize_request = None;
+                    }
+                }
+
+                // Shader/config hot-reload: swap the pipeline or the clear
+                // color and regenerate command buffers, exactly like a resize.
+                if let Some(reload_event) = hot_reload.poll() {
+                    match reload_event {
+                        ReloadEvent::Shaders => {
+                            let logical_device =
+                                graphical_engine.lock().unwrap().get_logical_device();
+
+                            if let Some(new_vertex_shader) = recompile_shader(
+                                Path::new("shaders/007_basic_triangle_instanced.vert"),
+                                shaderc::ShaderKind::Vertex,
+                                logical_device.clone(),
+                            ) {
+                                vertex_shader = new_vertex_shader;
+                            }
+
+                            if let Some(new_fragment_shader) = recompile_shader(
+                                Path::new("shaders/007_basic_triangle_instanced.frag"),
+                                shaderc::ShaderKind::Fragment,
+                                logical_device,
+                            ) {
+                                fragment_shader = new_fragment_shader;
+                            }
+
+                            let new_pipeline = create_pipeline(
+                                vertex_shader.clone(),
+                                fragment_shader.clone(),
+                                current_size,
+                                render_pass.clone(),
+                                graphical_engine.lock().unwrap().get_logical_device(),
+                            );
+                            let mut pipeline_lock = pipeline.lock().unwrap();
+                            *pipeline_lock = new_pipeline;
+
+                            command_buffers = create_instanced_command_buffers(
+                                frame_buffers.lock().unwrap().clone(),
+                                graphical_engine.clone(),
+                                pipeline_lock.clone(),
+                                vertex_buffer.clone(),
+                                index_buffer.clone(),
+                                instance_buffer.clone(),
+                                &camera,
+                                aspect,
+                                render_settings.clear_color,
+                            );
+
+                            log::info!("Reloaded shaders");
+                        }
+                        ReloadEvent::Config => {
+                            let previous_present_mode = render_settings.present_mode;
+                            render_settings = load_render_settings(&config_path, render_settings);
+
+                            if render_settings.present_mode != previous_present_mode {
+                                graphical_engine
+                                    .lock()
+                                    .unwrap()
+                                    .set_present_mode(render_settings.present_mode);
+                                // Takes effect on the next swapchain (re)creation.
+                                recreate_swapchain = true;
+                            }
+
+                            command_buffers = create_instanced_command_buffers(
+                                frame_buffers.lock().unwrap().clone(),
+                                graphical_engine.clone(),
+                                pipeline.lock().unwrap().clone(),
+                                vertex_buffer.clone(),
+                                index_buffer.clone(),
+                                instance_buffer.clone(),
+                                &camera,
+                                aspect,
+                                render_settings.clear_color,
+                            );
+
+                            log::info!("Reloaded engine config");
+                        }
+                    }
+                }
+
+                match graphical_engine
+                    .lock()
+                    .unwrap()
+                    .render_frame(&command_buffers)
+                {
+                    RenderOutcome::Rendered => {}
+                    RenderOutcome::Suboptimal => recreate_swapchain = true,
+                    RenderOutcome::OutOfDate => recreate_swapchain = true,
+                }
+            }
+            Event::RedrawRequested(_) => {}
+            Event::MainEventsCleared => {}
+            _ => (),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translation_leaves_rotation_scale_identity() {
+        let instance = SInstanceData::translation(2.0, -3.0);
+
+        assert_eq!(instance.model_col0, [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(instance.model_col1, [0.0, 1.0, 0.0, 0.0]);
+        assert_eq!(instance.model_col2, [0.0, 0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn translation_places_xy_in_last_column() {
+        let instance = SInstanceData::translation(2.0, -3.0);
+
+        assert_eq!(instance.model_col3, [2.0, -3.0, 0.0, 1.0]);
+    }
+
+    fn default_settings() -> RenderSettings {
+        RenderSettings {
+            clear_color: [0.1, 0.1, 0.1, 1.0],
+            present_mode: PresentMode::Fifo,
+        }
+    }
+
+    fn write_temp_config(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn load_render_settings_falls_back_when_file_missing() {
+        let fallback = default_settings();
+        let path = std::env::temp_dir().join("007_basic_triangle_does_not_exist.toml");
+        let _ = std::fs::remove_file(&path);
+
+        let settings = load_render_settings(&path, fallback);
+
+        assert_eq!(settings.clear_color, fallback.clear_color);
+        assert_eq!(settings.present_mode, fallback.present_mode);
+    }
+
+    #[test]
+    fn load_render_settings_falls_back_on_malformed_clear_color() {
+        let fallback = default_settings();
+        let path = write_temp_config(
+            "007_basic_triangle_malformed.toml",
+            "clear_color = [not, a, number]\n",
+        );
+
+        let settings = load_render_settings(&path, fallback);
+
+        assert_eq!(settings.clear_color, fallback.clear_color);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn load_render_settings_parses_valid_lines() {
+        let path = write_temp_config(
+            "007_basic_triangle_valid.toml",
+            "clear_color = [0.2, 0.3, 0.4, 1.0]\npresent_mode = \"mailbox\"\n",
+        );
+
+        let settings = load_render_settings(&path, default_settings());
+
+        assert_eq!(settings.clear_color, [0.2, 0.3, 0.4, 1.0]);
+        assert_eq!(settings.present_mode, PresentMode::Mailbox);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,395 @@
+use std::sync::Arc;
+
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo},
+        PrimaryAutoCommandBuffer,
+    },
+    device::{
+        physical::{PhysicalDevice, PhysicalDeviceType},
+        Device, DeviceCreateInfo, DeviceExtensions, Queue, QueueCreateInfo, QueueFlags,
+    },
+    image::{view::ImageView, ImageUsage, SwapchainImage},
+    instance::{Instance, InstanceCreateInfo},
+    memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator},
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass},
+    swapchain::{
+        self, AcquireError, PresentMode, Surface, Swapchain, SwapchainCreateInfo,
+        SwapchainPresentInfo,
+    },
+    sync::{self, FlushError, GpuFuture},
+    VulkanLibrary,
+};
+use winit::window::Window;
+
+// Lifecycle hook every engine implements, so examples can shut down
+// uniformly regardless of which concrete engine they're holding.
+pub trait AbstractEngine {
+    fn kill(&mut self);
+}
+
+// Thin handle around the Vulkan device and the one queue this engine submits
+// work to. Cloned around freely since the device and queue are already
+// `Arc`s and the queue family index is a plain `u32`.
+#[derive(Clone)]
+pub struct LogicalDevice {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    queue_family_index: u32,
+}
+
+impl LogicalDevice {
+    pub fn get_device(&self) -> Arc<Device> {
+        self.device.clone()
+    }
+
+    pub fn get_first_queue(&self) -> Arc<Queue> {
+        self.queue.clone()
+    }
+
+    pub fn get_queue_family_index(&self) -> u32 {
+        self.queue_family_index
+    }
+}
+
+// Outcome of a single `GraphicalEngine::render_frame` call, so the caller can
+// react (e.g. skip presenting, or schedule a swapchain recreation) without
+// the render loop blocking on the GPU.
+pub enum RenderOutcome {
+    Rendered,
+    Suboptimal,
+    OutOfDate,
+}
+
+// Owns the swapchain, the device/queue it was created from, and the
+// frame-in-flight fence/future state needed to submit and present safely.
+pub struct GraphicalEngine {
+    surface: Arc<Surface>,
+    logical_device: Arc<LogicalDevice>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    swapchain: Arc<Swapchain>,
+    images: Vec<Arc<SwapchainImage>>,
+    present_mode: PresentMode,
+    // One fence-future slot per swapchain image plus the tail of the previous
+    // frame, so submissions never reuse a future the GPU hasn't finished with.
+    frame_futures: Vec<Option<Box<dyn GpuFuture>>>,
+    previous_frame_end: Option<Box<dyn GpuFuture>>,
+}
+
+impl GraphicalEngine {
+    pub fn make_instance() -> Arc<Instance> {
+        let library = VulkanLibrary::new().expect("no local Vulkan library/DLL");
+        let required_extensions = vulkano_win::required_extensions(&library);
+
+        Instance::new(
+            library,
+            InstanceCreateInfo {
+                enabled_extensions: required_extensions,
+                enumerate_portability: true,
+                ..Default::default()
+            },
+        )
+        .expect("failed to create instance")
+    }
+
+    pub fn new(instance: Arc<Instance>, surface: Arc<Surface>) -> Self {
+        let device_extensions = DeviceExtensions {
+            khr_swapchain: true,
+            ..DeviceExtensions::empty()
+        };
+
+        let (physical_device, queue_family_index) =
+            select_physical_device(&instance, &surface, &device_extensions);
+
+        let (device, mut queues) = Device::new(
+            physical_device.clone(),
+            DeviceCreateInfo {
+                enabled_extensions: device_extensions,
+                queue_create_infos: vec![QueueCreateInfo {
+                    queue_family_index,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        )
+        .expect("failed to create device");
+        let queue = queues.next().expect("device created with no queues");
+
+        let logical_device = Arc::new(LogicalDevice {
+            device: device.clone(),
+            queue,
+            queue_family_index,
+        });
+
+        let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(
+            device,
+            StandardCommandBufferAllocatorCreateInfo::default(),
+        ));
+
+        let present_mode = PresentMode::Fifo;
+        let (swapchain, images) =
+            create_swap_chain(&physical_device, &logical_device, &surface, present_mode);
+        let image_count = images.len();
+
+        Self {
+            surface,
+            logical_device,
+            command_buffer_allocator,
+            swapchain,
+            images,
+            present_mode,
+            frame_futures: (0..image_count).map(|_| None).collect(),
+            previous_frame_end: None,
+        }
+    }
+
+    pub fn get_logical_device(&self) -> Arc<LogicalDevice> {
+        self.logical_device.clone()
+    }
+
+    pub fn get_swap_chain(&self) -> Arc<Swapchain> {
+        self.swapchain.clone()
+    }
+
+    pub fn get_command_buffer_allocator(&self) -> Arc<StandardCommandBufferAllocator> {
+        self.command_buffer_allocator.clone()
+    }
+
+    // The present mode only takes effect on the next swapchain (re)creation,
+    // same as a resize, so callers that change it should also set the
+    // `recreate_swapchain` flag they already use for resizes.
+    pub fn set_present_mode(&mut self, mode: PresentMode) {
+        self.present_mode = mode;
+    }
+
+    pub fn create_render_pass(&self) -> Arc<RenderPass> {
+        vulkano::single_pass_renderpass!(
+            self.logical_device.get_device(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: self.swapchain.image_format(),
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {},
+            },
+        )
+        .expect("failed to create render pass")
+    }
+
+    pub fn create_frame_buffers(&self, render_pass: Arc<RenderPass>) -> Vec<Arc<Framebuffer>> {
+        self.images
+            .iter()
+            .map(|image| {
+                let view = ImageView::new_default(image.clone()).unwrap();
+                Framebuffer::new(
+                    render_pass.clone(),
+                    FramebufferCreateInfo {
+                        attachments: vec![view],
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+            })
+            .collect()
+    }
+
+    pub fn recreate_swap_chain_and_images(
+        &mut self,
+        render_pass: Arc<RenderPass>,
+    ) -> Option<Vec<Arc<Framebuffer>>> {
+        let window = self
+            .surface
+            .object()?
+            .downcast_ref::<Window>()?;
+        let image_extent: [u32; 2] = window.inner_size().into();
+        if image_extent.contains(&0) {
+            // Minimized; nothing sensible to recreate yet.
+            return None;
+        }
+
+        let create_info = SwapchainCreateInfo {
+            image_extent,
+            present_mode: self.present_mode,
+            ..self.swapchain.create_info()
+        };
+
+        let (swapchain, images) = self.swapchain.recreate(create_info).ok()?;
+        self.swapchain = swapchain;
+        self.images = images;
+        self.frame_futures = (0..self.images.len()).map(|_| None).collect();
+
+        Some(self.create_frame_buffers(render_pass))
+    }
+
+    pub fn create_index_buffer(
+        &self,
+        memory_allocator: &StandardMemoryAllocator,
+        indices: Vec<u32>,
+    ) -> Subbuffer<[u32]> {
+        Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::INDEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            indices,
+        )
+        .unwrap()
+    }
+
+    pub fn render_frame(
+        &mut self,
+        command_buffers: &[Arc<PrimaryAutoCommandBuffer>],
+    ) -> RenderOutcome {
+        let (image_i, suboptimal, acquire_future) =
+            match swapchain::acquire_next_image(self.swapchain.clone(), None) {
+                Ok(r) => r,
+                Err(AcquireError::OutOfDate) => return RenderOutcome::OutOfDate,
+                Err(e) => panic!("Failed to acquire next image: {:?}", e),
+            };
+
+        // The slot for this image is the fence-future of whatever last used
+        // this particular swapchain image; clean it up and fold it into the
+        // join so we never resubmit against a fence that's still in flight.
+        let image_fence = match &mut self.frame_futures[image_i as usize] {
+            Some(future) => {
+                future.cleanup_finished();
+                self.frame_futures[image_i as usize].take().unwrap()
+            }
+            None => self
+                .previous_frame_end
+                .take()
+                .unwrap_or_else(|| sync::now(self.logical_device.get_device()).boxed()),
+        };
+
+        let execution = image_fence
+            .join(acquire_future)
+            .then_execute(
+                self.logical_device.get_first_queue(),
+                command_buffers[image_i as usize].clone(),
+            )
+            .unwrap()
+            .then_swapchain_present(
+                self.logical_device.get_first_queue(),
+                SwapchainPresentInfo::swapchain_image_index(self.swapchain.clone(), image_i),
+            )
+            .then_signal_fence_and_flush();
+
+        match execution {
+            Ok(future) => {
+                // The join chain above bottoms out in `Box<dyn GpuFuture>`
+                // (not `+ Send + Sync`), so clippy can't see that this Arc
+                // never actually crosses a thread boundary; it's only ever
+                // touched from the render loop while holding the engine lock.
+                #[allow(clippy::arc_with_non_send_sync)]
+                let future = Arc::new(future);
+                self.previous_frame_end = Some(future.clone().boxed());
+                self.frame_futures[image_i as usize] = Some(future.boxed());
+            }
+            Err(FlushError::OutOfDate) => return RenderOutcome::OutOfDate,
+            Err(e) => {
+                log::error!("Failed to flush future: {:?}", e);
+            }
+        }
+
+        if suboptimal {
+            RenderOutcome::Suboptimal
+        } else {
+            RenderOutcome::Rendered
+        }
+    }
+}
+
+impl AbstractEngine for GraphicalEngine {
+    fn kill(&mut self) {
+        if let Some(mut future) = self.previous_frame_end.take() {
+            future.cleanup_finished();
+        }
+        for future in self.frame_futures.iter_mut().flatten() {
+            future.cleanup_finished();
+        }
+    }
+}
+
+fn select_physical_device(
+    instance: &Arc<Instance>,
+    surface: &Arc<Surface>,
+    device_extensions: &DeviceExtensions,
+) -> (Arc<PhysicalDevice>, u32) {
+    instance
+        .enumerate_physical_devices()
+        .expect("failed to enumerate physical devices")
+        .filter(|p| p.supported_extensions().contains(device_extensions))
+        .filter_map(|p| {
+            p.queue_family_properties()
+                .iter()
+                .enumerate()
+                .position(|(i, q)| {
+                    q.queue_flags.intersects(QueueFlags::GRAPHICS)
+                        && p.surface_support(i as u32, surface).unwrap_or(false)
+                })
+                .map(|i| (p, i as u32))
+        })
+        .min_by_key(|(p, _)| match p.properties().device_type {
+            PhysicalDeviceType::DiscreteGpu => 0,
+            PhysicalDeviceType::IntegratedGpu => 1,
+            PhysicalDeviceType::VirtualGpu => 2,
+            PhysicalDeviceType::Cpu => 3,
+            PhysicalDeviceType::Other => 4,
+            _ => 5,
+        })
+        .expect("no suitable physical device found")
+}
+
+fn create_swap_chain(
+    physical_device: &Arc<PhysicalDevice>,
+    logical_device: &Arc<LogicalDevice>,
+    surface: &Arc<Surface>,
+    present_mode: PresentMode,
+) -> (Arc<Swapchain>, Vec<Arc<SwapchainImage>>) {
+    let caps = physical_device
+        .surface_capabilities(surface, Default::default())
+        .expect("failed to get surface capabilities");
+
+    let image_format = Some(
+        physical_device
+            .surface_formats(surface, Default::default())
+            .unwrap()[0]
+            .0,
+    );
+    let window = surface
+        .object()
+        .unwrap()
+        .downcast_ref::<Window>()
+        .unwrap();
+    let image_extent: [u32; 2] = window.inner_size().into();
+
+    Swapchain::new(
+        logical_device.get_device(),
+        surface.clone(),
+        SwapchainCreateInfo {
+            min_image_count: caps.min_image_count + 1,
+            image_format,
+            image_extent,
+            image_usage: ImageUsage::COLOR_ATTACHMENT,
+            composite_alpha: caps
+                .supported_composite_alpha
+                .into_iter()
+                .next()
+                .expect("surface supports no composite alpha modes"),
+            present_mode,
+            ..Default::default()
+        },
+    )
+    .expect("failed to create swapchain")
+}